@@ -0,0 +1,53 @@
+//! Error types for cargo-edit, built on `error_chain`.
+
+use std::path::PathBuf;
+
+error_chain! {
+    errors {
+        /// An empty crate name was provided.
+        EmptyCrateName {
+            description("empty crate name")
+            display("crate name cannot be empty")
+        }
+        /// No crate with the given name exists in the registry index.
+        NoCrate(name: String) {
+            description("crate not found in registry")
+            display("the crate `{}` could not be found in registry index", name)
+        }
+        /// None of a crate's versions satisfy the requested constraints
+        /// (prerelease flag, yanked status, or version requirement).
+        NoVersionsAvailable {
+            description("no versions available")
+            display("no available versions found")
+        }
+        /// A local registry checkout has no `refs/remotes/origin/*` ref to
+        /// read the index tree from.
+        MissingRegistraryCheckout(path: PathBuf) {
+            description("could not find registry checkout")
+            display("could not find a checkout at `{}`", path.display())
+        }
+        /// A path read out of a git tree was not valid UTF-8.
+        NonUnicodeGitPath {
+            description("non-unicode path in git repository")
+            display("path in git repository was not valid UTF-8")
+        }
+        /// A registry index summary line could not be parsed as JSON.
+        InvalidSummaryJson {
+            description("invalid summary")
+            display("invalid JSON found in registry index summary")
+        }
+        /// `Cargo.toml` could not be parsed.
+        ParseCargoToml {
+            description("parse error")
+            display("unable to parse Cargo.toml")
+        }
+    }
+
+    foreign_links {
+        Io(std::io::Error);
+        Git(git2::Error);
+        Reqwest(reqwest::Error);
+        Semver(semver::Error);
+        Json(serde_json::Error);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::errors::*;
-use crate::registry::{RegistryIndex, RegistryReq};
+use crate::registry::{IndexProtocol, RegistryIndex, RegistryReq};
 use crate::{Dependency, Manifest};
 use regex::Regex;
 use reqwest::Proxy;
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -15,6 +17,147 @@ struct CrateVersion {
     #[serde(rename = "vers")]
     version: semver::Version,
     yanked: bool,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    deps: Vec<RegistryDep>,
+    #[serde(default)]
+    cksum: String,
+    #[serde(default)]
+    links: Option<String>,
+}
+
+/// A single dependency entry as recorded in a registry index summary.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RegistryDep {
+    pub name: String,
+    pub req: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub default_features: bool,
+    pub target: Option<String>,
+    pub kind: Option<String>,
+    pub registry: Option<String>,
+    pub package: Option<String>,
+}
+
+/// A single version of a crate, as reported by [`get_crate_info`].
+#[derive(Clone, Debug)]
+pub struct CrateVersionInfo {
+    pub version: semver::Version,
+    pub yanked: bool,
+    pub deps: Vec<RegistryDep>,
+    pub cksum: String,
+    pub links: Option<String>,
+}
+
+/// Everything `cargo add` needs to know about a crate: its published
+/// versions and the feature table of the latest matching one.
+#[derive(Clone, Debug)]
+pub struct CrateInfo {
+    pub name: String,
+    pub versions: Vec<CrateVersionInfo>,
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+/// Query a registry index for every known version of a crate, plus the
+/// feature table of its latest non-yanked version.
+///
+/// This lets callers validate that a requested `--features` flag names
+/// features that actually exist, and supports listing available versions
+/// and their yanked status, similar to `cargo info`.
+pub fn get_crate_info(crate_name: &str, registry: RegistryReq) -> Result<CrateInfo> {
+    let registry_index = registry.index_url()?;
+    let crate_versions = fuzzy_query_registry_index(crate_name, &registry_index)?;
+
+    build_crate_info(&crate_versions)
+}
+
+/// Assemble a [`CrateInfo`] from the raw summaries of every known version of
+/// a crate, pulled out of [`get_crate_info`] so the
+/// `features`/`deps`/`cksum`/`links` plumbing can be unit tested without a
+/// real registry index.
+fn build_crate_info(crate_versions: &[CrateVersion]) -> Result<CrateInfo> {
+    let latest = crate_versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .max_by_key(|v| v.version.clone())
+        .ok_or(ErrorKind::NoVersionsAvailable)?;
+
+    Ok(CrateInfo {
+        name: latest.name.clone(),
+        features: latest.features.clone(),
+        versions: crate_versions
+            .iter()
+            .map(|v| CrateVersionInfo {
+                version: v.version.clone(),
+                yanked: v.yanked,
+                deps: v.deps.clone(),
+                cksum: v.cksum.clone(),
+                links: v.links.clone(),
+            })
+            .collect(),
+    })
+}
+
+#[test]
+fn build_crate_info_surfaces_features_deps_cksum_and_links() {
+    let versions: Vec<CrateVersion> = serde_json::from_str(
+        r#"[
+        {
+          "name": "foo",
+          "vers": "1.1.0",
+          "yanked": false,
+          "features": { "default": ["std"], "std": [] },
+          "deps": [
+            { "name": "bar", "req": "^1.0", "features": ["derive"], "optional": true,
+              "default_features": false, "target": null, "kind": "normal",
+              "registry": null, "package": null }
+          ],
+          "cksum": "deadbeef",
+          "links": "libfoo"
+        },
+        {
+          "name": "foo",
+          "vers": "1.0.0",
+          "yanked": true,
+          "cksum": "cafebabe"
+        }
+      ]"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let info = build_crate_info(&versions).unwrap();
+
+    assert_eq!(info.name, "foo");
+    assert_eq!(
+        info.features.get("default"),
+        Some(&vec!["std".to_string()])
+    );
+
+    let latest = info
+        .versions
+        .iter()
+        .find(|v| v.version.to_string() == "1.1.0")
+        .unwrap();
+    assert_eq!(latest.cksum, "deadbeef");
+    assert_eq!(latest.links.as_deref(), Some("libfoo"));
+    assert_eq!(latest.deps.len(), 1);
+    assert_eq!(latest.deps[0].name, "bar");
+    assert_eq!(latest.deps[0].req, "^1.0");
+    assert!(latest.deps[0].optional);
+
+    let yanked = info
+        .versions
+        .iter()
+        .find(|v| v.version.to_string() == "1.0.0")
+        .unwrap();
+    assert!(yanked.yanked);
+    assert_eq!(yanked.cksum, "cafebabe");
+    assert!(yanked.links.is_none());
 }
 
 /// Query latest version from a registry index
@@ -52,9 +195,9 @@ pub fn get_latest_dependency(
         return Err(ErrorKind::EmptyCrateName.into());
     }
 
-    let registry_cache = registry.index_url()?.cache_path()?;
+    let registry_index = registry.index_url()?;
 
-    let crate_versions = fuzzy_query_registry_index(crate_name, &registry_cache)?;
+    let crate_versions = fuzzy_query_registry_index(crate_name, &registry_index)?;
 
     let dep = read_latest_version(&crate_versions, flag_allow_prerelease)?;
 
@@ -65,6 +208,51 @@ pub fn get_latest_dependency(
     Ok(dep)
 }
 
+/// Query a registry index for the newest version matching a `VersionReq`.
+///
+/// Unlike [`get_latest_dependency`], which always returns the global
+/// newest version, this honors an existing caret/range constraint (e.g. from
+/// `Cargo.toml`, or a `cargo add foo@^1.2` pin) and returns the newest
+/// non-yanked version satisfying it.
+pub fn get_dependency_matching(
+    crate_name: &str,
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+    registry: RegistryReq,
+) -> Result<Dependency> {
+    if env::var("CARGO_IS_TEST").is_ok() {
+        // We are in a simulated reality. Nothing is real here.
+        // FIXME: Use actual test handling code.
+        let new_version = if flag_allow_prerelease {
+            format!("{}--PRERELEASE_VERSION_TEST", crate_name)
+        } else {
+            match crate_name {
+                "test_breaking" => "0.2.0".to_string(),
+                "test_nonbreaking" => "0.1.1".to_string(),
+                other => format!("{}--CURRENT_VERSION_TEST", other),
+            }
+        };
+
+        return Ok(Dependency::new(crate_name).set_version(&new_version));
+    }
+
+    if crate_name.is_empty() {
+        return Err(ErrorKind::EmptyCrateName.into());
+    }
+
+    let registry_index = registry.index_url()?;
+
+    let crate_versions = fuzzy_query_registry_index(crate_name, &registry_index)?;
+
+    let dep = read_version_matching(&crate_versions, version_req, flag_allow_prerelease)?;
+
+    if dep.name != crate_name {
+        println!("WARN: Added `{}` instead of `{}`", dep.name, crate_name);
+    }
+
+    Ok(dep)
+}
+
 // Checks whether a version object is a stable release
 fn version_is_stable(version: &CrateVersion) -> bool {
     !version.version.is_prerelease()
@@ -87,8 +275,39 @@ fn read_latest_version(
     Ok(Dependency::new(name).set_version(&version))
 }
 
+/// Read the highest non-yanked version satisfying `version_req` from the
+/// Versions structure.
+fn read_version_matching(
+    versions: &[CrateVersion],
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+) -> Result<Dependency> {
+    let latest = versions
+        .iter()
+        .filter(|&v| flag_allow_prerelease || version_is_stable(v))
+        .filter(|&v| !v.yanked)
+        .filter(|&v| version_req.matches(&v.version))
+        .max_by_key(|&v| v.version.clone())
+        .ok_or(ErrorKind::NoVersionsAvailable)?;
+
+    let name = &latest.name;
+    let version = latest.version.to_string();
+    Ok(Dependency::new(name).set_version(&version))
+}
+
 /// update registry index for given project
 pub fn update_registry_index(registry: &RegistryIndex) -> Result<()> {
+    // A sparse index has no on-disk git checkout to update; just drop the
+    // cached summaries (and their `ETag`s) so the next lookup re-fetches
+    // them in full.
+    if registry.protocol() == IndexProtocol::Sparse {
+        let cache_dir = registry.sparse_cache_path()?;
+        if cache_dir.exists() {
+            fs::remove_dir_all(&cache_dir)?;
+        }
+        return Ok(());
+    }
+
     let registry_path = registry.cache_path()?;
 
     let colorchoice = if atty::is(atty::Stream::Stdout) {
@@ -258,6 +477,56 @@ fn get_no_latest_version_from_json_when_all_are_yanked() {
     assert!(read_latest_version(&versions, false).is_err());
 }
 
+#[test]
+fn get_version_matching_req_from_json() {
+    let versions: Vec<CrateVersion> = serde_json::from_str(
+        r#"[
+        {
+          "name": "foo",
+          "vers": "2.0.0",
+          "yanked": false
+        },
+        {
+          "name": "foo",
+          "vers": "1.5.0",
+          "yanked": false
+        },
+        {
+          "name": "foo",
+          "vers": "1.2.0",
+          "yanked": false
+        }
+      ]"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("^1").unwrap();
+    assert_eq!(
+        read_version_matching(&versions, &req, false)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "1.5.0"
+    );
+}
+
+#[test]
+fn get_no_version_matching_req_when_none_satisfy() {
+    let versions: Vec<CrateVersion> = serde_json::from_str(
+        r#"[
+        {
+          "name": "foo",
+          "vers": "2.0.0",
+          "yanked": false
+        }
+      ]"#,
+    )
+    .expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("^1").unwrap();
+    assert!(read_version_matching(&versions, &req, false).is_err());
+}
+
 /// Gets the checkedout branch name of .cargo/registry/index/github.com-*/.git/refs or
 /// .cargo/registry/index/github.com-*/refs for bare git repository
 fn get_checkout_name(registry_path: impl AsRef<Path>) -> Result<String> {
@@ -277,12 +546,30 @@ fn get_checkout_name(registry_path: impl AsRef<Path>) -> Result<String> {
         .map_err(|_| ErrorKind::NonUnicodeGitPath)?)
 }
 
-/// Fuzzy query crate from registry index
+/// Fuzzy query crate from registry index, dispatching to the git or sparse
+/// HTTP backend depending on the registry's configured protocol.
 fn fuzzy_query_registry_index(
     crate_name: impl Into<String>,
-    registry_path: impl AsRef<Path>,
+    registry: &RegistryIndex,
 ) -> Result<Vec<CrateVersion>> {
     let crate_name = crate_name.into();
+    let mut names = gen_fuzzy_crate_names(crate_name.clone())?;
+    if let Some(index) = names.iter().position(|x| *x == crate_name) {
+        // ref: https://github.com/killercup/cargo-edit/pull/317#discussion_r307365704
+        names.swap(index, 0);
+    }
+
+    match registry.protocol() {
+        IndexProtocol::Git => fuzzy_query_git_index(&crate_name, &names, registry.cache_path()?),
+        IndexProtocol::Sparse => fuzzy_query_sparse_index(&crate_name, &names, registry),
+    }
+}
+
+fn fuzzy_query_git_index(
+    crate_name: &str,
+    names: &[String],
+    registry_path: impl AsRef<Path>,
+) -> Result<Vec<CrateVersion>> {
     let remotes = PathBuf::from("refs/remotes/origin/");
     let repo = git2::Repository::open(&registry_path)?;
     let tree = repo
@@ -294,85 +581,389 @@ fn fuzzy_query_registry_index(
         )?
         .peel_to_tree()?;
 
-    let mut names = gen_fuzzy_crate_names(crate_name.clone())?;
-    if let Some(index) = names.iter().position(|x| *x == crate_name) {
-        // ref: https://github.com/killercup/cargo-edit/pull/317#discussion_r307365704
-        names.swap(index, 0);
-    }
-
     for the_name in names {
-        let file = match tree.get_path(&PathBuf::from(summary_raw_path(&the_name))) {
+        let file = match tree.get_path(&PathBuf::from(summary_raw_path(the_name))) {
             Ok(x) => x.to_object(&repo)?.peel_to_blob()?,
             Err(_) => continue,
         };
         let content = String::from_utf8(file.content().to_vec())
             .map_err(|_| ErrorKind::InvalidSummaryJson)?;
 
-        return content
-            .lines()
-            .map(|line: &str| {
-                serde_json::from_str::<CrateVersion>(line)
-                    .map_err(|_| ErrorKind::InvalidSummaryJson.into())
-            })
-            .collect::<Result<Vec<CrateVersion>>>();
+        return parse_summary_lines(&content);
     }
-    Err(ErrorKind::NoCrate(crate_name).into())
+    Err(ErrorKind::NoCrate(crate_name.to_string()).into())
 }
 
-fn get_crate_name_from_repository<T>(repo: &str, matcher: &Regex, url_template: T) -> Result<String>
-where
-    T: Fn(&str, &str) -> String,
-{
-    matcher
-        .captures(repo)
-        .ok_or_else(|| "Unable to parse git repo URL".into())
-        .and_then(|cap| match (cap.get(1), cap.get(2)) {
-            (Some(user), Some(repo)) => {
-                let url = url_template(user.as_str(), repo.as_str());
-                let data: Result<Manifest> = get_cargo_toml_from_git_url(&url)
-                    .and_then(|m| m.parse().chain_err(|| ErrorKind::ParseCargoToml));
-                data.and_then(|ref manifest| get_name_from_manifest(manifest))
+/// Fuzzy query a sparse (HTTP) registry index.
+///
+/// Unlike a git checkout, a sparse index cannot be listed to find which
+/// fuzzy name variant actually exists, so each candidate is requested in
+/// turn and a 404 is treated as a miss, falling through to the next name.
+fn fuzzy_query_sparse_index(
+    crate_name: &str,
+    names: &[String],
+    registry: &RegistryIndex,
+) -> Result<Vec<CrateVersion>> {
+    for the_name in names {
+        if let Some(content) = get_sparse_summary(the_name, registry)? {
+            return parse_summary_lines(&content);
+        }
+    }
+    Err(ErrorKind::NoCrate(crate_name.to_string()).into())
+}
+
+fn parse_summary_lines(content: &str) -> Result<Vec<CrateVersion>> {
+    content
+        .lines()
+        .map(|line: &str| {
+            serde_json::from_str::<CrateVersion>(line)
+                .map_err(|_| ErrorKind::InvalidSummaryJson.into())
+        })
+        .collect()
+}
+
+#[test]
+fn parse_summary_lines_reads_one_version_per_line() {
+    let versions = parse_summary_lines(
+        "{\"name\":\"foo\",\"vers\":\"0.1.0\",\"yanked\":false}\n\
+         {\"name\":\"foo\",\"vers\":\"0.2.0\",\"yanked\":true}",
+    )
+    .unwrap();
+
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0].version.to_string(), "0.1.0");
+    assert!(!versions[0].yanked);
+    assert_eq!(versions[1].version.to_string(), "0.2.0");
+    assert!(versions[1].yanked);
+}
+
+#[test]
+fn parse_summary_lines_rejects_malformed_json() {
+    assert!(parse_summary_lines("not json").is_err());
+}
+
+#[test]
+fn sparse_index_config_defaults_auth_required_to_false() {
+    let config: SparseIndexConfig = serde_json::from_str("{}").unwrap();
+    assert!(!config.auth_required);
+
+    let config: SparseIndexConfig =
+        serde_json::from_str(r#"{"auth-required":true}"#).unwrap();
+    assert!(config.auth_required);
+}
+
+/// Fetch a single crate's summary file from a sparse HTTP index, honoring a
+/// local `ETag` cache so repeated `cargo add`/`cargo upgrade` invocations
+/// don't re-download unchanged summaries. Returns `Ok(None)` when the
+/// registry has no such crate (a `404`).
+fn get_sparse_summary(crate_name: &str, registry: &RegistryIndex) -> Result<Option<String>> {
+    let cache_dir = registry.sparse_cache_path()?;
+    let cache_file = cache_dir.join(summary_raw_path(crate_name));
+    let etag_file = cache_file.with_extension("etag");
+
+    let mut req = default_http_client()?.get(format!(
+        "{}{}",
+        registry.base_url(),
+        summary_raw_path(crate_name)
+    ));
+    if let Ok(etag) = fs::read_to_string(&etag_file) {
+        req = req.header(reqwest::header::IF_NONE_MATCH, etag.trim());
+    }
+    if sparse_auth_required(registry)? {
+        if let Some(token) = registry.token()? {
+            req = req.header(reqwest::header::AUTHORIZATION, token);
+        }
+    }
+
+    let res = req.send().chain_err(|| "sparse registry request failed")?;
+    match res.status() {
+        reqwest::StatusCode::NOT_FOUND => Ok(None),
+        reqwest::StatusCode::NOT_MODIFIED => Ok(Some(
+            fs::read_to_string(&cache_file).chain_err(|| "missing cached registry summary")?,
+        )),
+        _ => {
+            let res = res
+                .error_for_status()
+                .chain_err(|| "sparse registry request failed")?;
+            let etag = res
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = res.text().chain_err(|| "registry response not valid UTF-8")?;
+
+            if let Some(parent) = cache_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&cache_file, &body)?;
+            if let Some(etag) = etag {
+                fs::write(&etag_file, etag)?;
             }
-            _ => Err("Git repo url seems incomplete".into()),
+            Ok(Some(body))
+        }
+    }
+}
+
+/// A `reqwest` client configured with cargo-edit's default timeout and
+/// environment proxy settings, shared by every HTTP call this module makes
+/// (sparse index lookups and fetching a repo's `Cargo.toml`).
+fn default_http_client() -> Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(get_default_timeout())
+        .proxy(Proxy::custom(|u| env_proxy::for_url(u).to_url()))
+        .build()
+        .chain_err(|| "unable to build HTTP client")
+}
+
+/// Whether `registry`'s sparse index requires an `Authorization` header on
+/// index requests.
+///
+/// Discovered the way real cargo does it (RFC 3139): by fetching the
+/// index's own `config.json` and reading its `auth-required` field, cached
+/// alongside the rest of the sparse index cache so it's only fetched once.
+/// Falls back to [`RegistryIndex::local_auth_override`] (and then `false`)
+/// if the registry can't be reached, e.g. while offline.
+fn sparse_auth_required(registry: &RegistryIndex) -> Result<bool> {
+    let cache_file = registry.sparse_cache_path()?.join("config.json");
+    if let Ok(cached) = fs::read_to_string(&cache_file) {
+        if let Ok(config) = serde_json::from_str::<SparseIndexConfig>(&cached) {
+            return Ok(config.auth_required);
+        }
+    }
+
+    let url = format!("{}config.json", registry.base_url());
+    let fetched = default_http_client()?
+        .get(&url)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .ok()
+        .and_then(|r| r.text().ok());
+
+    if let Some(body) = fetched {
+        if let Some(parent) = cache_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&cache_file, &body)?;
+        if let Ok(config) = serde_json::from_str::<SparseIndexConfig>(&body) {
+            return Ok(config.auth_required);
+        }
+    }
+
+    Ok(registry.local_auth_override()?.unwrap_or(false))
+}
+
+/// The subset of a sparse index's `config.json` (RFC 3139) that we care
+/// about.
+#[derive(Deserialize)]
+struct SparseIndexConfig {
+    #[serde(default, rename = "auth-required")]
+    auth_required: bool,
+}
+
+/// A git forge this crate-name resolver knows how to talk to: a pattern
+/// matching its repository URLs (capturing the owner and repo name), and a
+/// template for the raw `Cargo.toml` URL of a given branch.
+///
+/// The template may use the `{user}`, `{repo}`, and `{branch}` placeholders.
+pub struct GitHostProvider {
+    matcher: Regex,
+    raw_url_template: String,
+}
+
+impl GitHostProvider {
+    /// Build a provider from a capturing regex and a raw-URL template, e.g.
+    /// for a self-hosted GitLab instance:
+    ///
+    /// ```ignore
+    /// GitHostProvider::new(
+    ///     r"^https://git\.example\.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|\.git)?$",
+    ///     "https://git.example.com/{user}/{repo}/raw/{branch}/Cargo.toml",
+    /// )?
+    /// ```
+    pub fn new(matcher: &str, raw_url_template: impl Into<String>) -> Result<Self> {
+        Ok(GitHostProvider {
+            matcher: Regex::new(matcher).chain_err(|| "invalid git host matcher regex")?,
+            raw_url_template: raw_url_template.into(),
         })
+    }
+
+    fn raw_url(&self, user: &str, repo: &str, branch: &str) -> String {
+        self.raw_url_template
+            .replace("{user}", user)
+            .replace("{repo}", repo)
+            .replace("{branch}", branch)
+    }
 }
 
-/// Query crate name by accessing a github repo Cargo.toml
+/// Branches tried, in order, when a host's default branch isn't known ahead
+/// of time.
+const FALLBACK_BRANCHES: &[&str] = &["master", "main"];
+
+fn github_provider() -> GitHostProvider {
+    GitHostProvider::new(
+        r"^https://github\.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|\.git)?$",
+        "https://raw.githubusercontent.com/{user}/{repo}/{branch}/Cargo.toml",
+    )
+    .unwrap()
+}
+
+fn gitlab_provider() -> GitHostProvider {
+    GitHostProvider::new(
+        r"^https://gitlab\.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|\.git)?$",
+        "https://gitlab.com/{user}/{repo}/raw/{branch}/Cargo.toml",
+    )
+    .unwrap()
+}
+
+fn bitbucket_provider() -> GitHostProvider {
+    GitHostProvider::new(
+        r"^https://bitbucket\.org/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|\.git)?$",
+        "https://bitbucket.org/{user}/{repo}/raw/{branch}/Cargo.toml",
+    )
+    .unwrap()
+}
+
+fn sourcehut_provider() -> GitHostProvider {
+    GitHostProvider::new(
+        r"^https://git\.sr\.ht/~([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)/?$",
+        "https://git.sr.ht/~{user}/{repo}/blob/{branch}/Cargo.toml",
+    )
+    .unwrap()
+}
+
+fn builtin_git_host_providers() -> Vec<GitHostProvider> {
+    vec![
+        github_provider(),
+        gitlab_provider(),
+        bitbucket_provider(),
+        sourcehut_provider(),
+    ]
+}
+
+/// Query crate name by accessing a repo's `Cargo.toml` on a known (or
+/// caller-supplied) git forge.
 ///
 /// The name will be returned as a string. This will fail, when
 ///
 /// - there is no Internet connection,
-/// - Cargo.toml is not present in the root of the master branch,
+/// - `repo` does not match any known host's URL shape,
+/// - `Cargo.toml` is not present on any of the host's fallback branches,
+/// - the response from the host is an error or in an incorrect format.
+pub fn get_crate_name_from_repository(repo: &str) -> Result<String> {
+    get_crate_name_from_providers(repo, &builtin_git_host_providers())
+}
+
+/// As [`get_crate_name_from_repository`], but tries `custom_provider` first
+/// so callers can point `cargo add <url>` at a self-hosted forge that isn't
+/// one of the builtin hosts.
+pub fn get_crate_name_from_repository_with_provider(
+    repo: &str,
+    custom_provider: GitHostProvider,
+) -> Result<String> {
+    let mut providers = vec![custom_provider];
+    providers.extend(builtin_git_host_providers());
+    get_crate_name_from_providers(repo, &providers)
+}
+
+fn get_crate_name_from_providers(repo: &str, providers: &[GitHostProvider]) -> Result<String> {
+    let provider = providers
+        .iter()
+        .find(|p| p.matcher.is_match(repo))
+        .ok_or("Unable to parse git repo URL: no known host matched")?;
+
+    let cap = provider
+        .matcher
+        .captures(repo)
+        .expect("just matched above");
+    match (cap.get(1), cap.get(2)) {
+        (Some(user), Some(repo_name)) => {
+            let mut last_err = None;
+            for branch in FALLBACK_BRANCHES {
+                let url = provider.raw_url(user.as_str(), repo_name.as_str(), branch);
+                match get_cargo_toml_from_git_url(&url)
+                    .and_then(|m| m.parse().chain_err(|| ErrorKind::ParseCargoToml))
+                    .and_then(|ref manifest: Manifest| get_name_from_manifest(manifest))
+                {
+                    Ok(name) => return Ok(name),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            Err(last_err.expect("FALLBACK_BRANCHES is non-empty"))
+        }
+        _ => Err("Git repo url seems incomplete".into()),
+    }
+}
+
+#[test]
+fn git_host_provider_matches_known_hosts() {
+    assert!(github_provider().matcher.is_match("https://github.com/user/repo"));
+    assert!(github_provider().matcher.is_match("https://github.com/user/repo.git"));
+    assert!(github_provider().matcher.is_match("https://github.com/user/repo/"));
+    assert!(!github_provider().matcher.is_match("https://gitlab.com/user/repo"));
+
+    assert!(gitlab_provider().matcher.is_match("https://gitlab.com/user/repo"));
+    assert!(bitbucket_provider().matcher.is_match("https://bitbucket.org/user/repo"));
+    assert!(sourcehut_provider().matcher.is_match("https://git.sr.ht/~user/repo"));
+}
+
+#[test]
+fn git_host_provider_templates_raw_url() {
+    assert_eq!(
+        github_provider().raw_url("user", "repo", "main"),
+        "https://raw.githubusercontent.com/user/repo/main/Cargo.toml"
+    );
+    assert_eq!(
+        gitlab_provider().raw_url("user", "repo", "master"),
+        "https://gitlab.com/user/repo/raw/master/Cargo.toml"
+    );
+}
+
+#[test]
+fn fallback_branches_try_master_before_main() {
+    assert_eq!(FALLBACK_BRANCHES, ["master", "main"]);
+}
+
+#[test]
+fn get_crate_name_from_github_rejects_non_github_urls() {
+    assert!(get_crate_name_from_github("https://gitlab.com/user/repo").is_err());
+}
+
+#[test]
+fn get_crate_name_from_gitlab_rejects_non_gitlab_urls() {
+    assert!(get_crate_name_from_gitlab("https://github.com/user/repo").is_err());
+}
+
+#[test]
+fn get_crate_name_from_repository_rejects_unknown_host() {
+    assert!(get_crate_name_from_repository("https://example.com/user/repo").is_err());
+}
+
+/// Query crate name by accessing a github repo Cargo.toml
+///
+/// Unlike [`get_crate_name_from_repository`], this only matches
+/// `github.com` URLs — a non-GitHub `repo` is rejected rather than silently
+/// resolved against some other host. This will fail, when
+///
+/// - there is no Internet connection,
+/// - `repo` is not a `github.com` repository URL,
+/// - Cargo.toml is not present on the `master` or `main` branch,
 /// - the response from github is an error or in an incorrect format.
 pub fn get_crate_name_from_github(repo: &str) -> Result<String> {
-    let re =
-        Regex::new(r"^https://github.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|.git)?$").unwrap();
-    get_crate_name_from_repository(repo, &re, |user, repo| {
-        format!(
-            "https://raw.githubusercontent.com/{user}/{repo}/master/Cargo.toml",
-            user = user,
-            repo = repo
-        )
-    })
+    get_crate_name_from_providers(repo, std::slice::from_ref(&github_provider()))
 }
 
 /// Query crate name by accessing a gitlab repo Cargo.toml
 ///
-/// The name will be returned as a string. This will fail, when
+/// Unlike [`get_crate_name_from_repository`], this only matches
+/// `gitlab.com` URLs — a non-GitLab `repo` is rejected rather than silently
+/// resolved against some other host. This will fail, when
 ///
 /// - there is no Internet connection,
-/// - Cargo.toml is not present in the root of the master branch,
+/// - `repo` is not a `gitlab.com` repository URL,
+/// - Cargo.toml is not present on the `master` or `main` branch,
 /// - the response from gitlab is an error or in an incorrect format.
 pub fn get_crate_name_from_gitlab(repo: &str) -> Result<String> {
-    let re =
-        Regex::new(r"^https://gitlab.com/([-_0-9a-zA-Z]+)/([-_0-9a-zA-Z]+)(/|.git)?$").unwrap();
-    get_crate_name_from_repository(repo, &re, |user, repo| {
-        format!(
-            "https://gitlab.com/{user}/{repo}/raw/master/Cargo.toml",
-            user = user,
-            repo = repo
-        )
-    })
+    get_crate_name_from_providers(repo, std::slice::from_ref(&gitlab_provider()))
 }
 
 /// Query crate name by accessing Cargo.toml in a local path
@@ -396,13 +987,8 @@ fn get_name_from_manifest(manifest: &Manifest) -> Result<String> {
 }
 
 fn get_cargo_toml_from_git_url(url: &str) -> Result<String> {
-    let mut clientb = reqwest::blocking::Client::builder();
-    clientb = clientb.timeout(get_default_timeout());
-    clientb = clientb.proxy(Proxy::custom(|u| {
-        env_proxy::for_url(u).to_url()
-    }));
-    let client = clientb.build().unwrap();
-    
+    let client = default_http_client()?;
+
     match client.get(url).send().and_then(|r| r.error_for_status()) {
         Err(e) => {
             Err(format!(
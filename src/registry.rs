@@ -0,0 +1,291 @@
+//! Representations of the registries a dependency may be looked up against.
+//!
+//! A [`RegistryReq`] is what the user asked for (crates.io, or an alternate
+//! registry by name). Resolving it against the environment
+//! (`CARGO_REGISTRIES_<NAME>_INDEX`) produces a [`RegistryIndex`], which knows the index URL and
+//! where its on-disk cache lives.
+
+use crate::errors::*;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// The protocol used to talk to a registry index.
+///
+/// Selected off the index URL scheme: `sparse+https://...` uses the sparse
+/// HTTP protocol (the default since Rust 1.70), as does any registry with
+/// `CARGO_REGISTRIES_<NAME>_PROTOCOL=sparse` set; anything else is checked
+/// out as a git repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexProtocol {
+    Git,
+    Sparse,
+}
+
+/// The registry a dependency should be looked up against, as requested by the
+/// user via `--registry` or the default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryReq {
+    /// The default, public crates.io registry.
+    CratesIo,
+    /// An alternate registry, referred to by the name it's configured under
+    /// in `.cargo/config.toml` (`[registries.<name>]`).
+    Alternate(String),
+}
+
+impl RegistryReq {
+    /// Resolve this request to the registry's index URL.
+    ///
+    /// crates.io resolves to its well-known sparse index. An alternate
+    /// registry is looked up via `CARGO_REGISTRIES_<NAME>_INDEX`, since
+    /// cargo-edit does not read index URLs out of `.cargo/config.toml`.
+    pub fn index_url(&self) -> Result<RegistryIndex> {
+        match self {
+            // Sparse HTTP has been the default registry protocol since Rust
+            // 1.70; resolving to the git index here would mean sparse-only
+            // setups (no on-disk git checkout) can never look up crates.io.
+            RegistryReq::CratesIo => Ok(RegistryIndex::new(
+                "crates.io".to_string(),
+                "sparse+https://index.crates.io/".to_string(),
+            )),
+            RegistryReq::Alternate(name) => {
+                let var = format!("CARGO_REGISTRIES_{}_INDEX", shouty_snake_case(name));
+                let url = env::var(&var).chain_err(|| {
+                    format!(
+                        "registry `{}` is not configured; set `{}`",
+                        name, var
+                    )
+                })?;
+                Ok(RegistryIndex::new(name.clone(), url))
+            }
+        }
+    }
+}
+
+impl fmt::Display for RegistryReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryReq::CratesIo => write!(f, "crates.io"),
+            RegistryReq::Alternate(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+fn shouty_snake_case(name: &str) -> String {
+    name.chars()
+        .map(|c| if c == '-' { '_' } else { c.to_ascii_uppercase() })
+        .collect()
+}
+
+/// A resolved registry index: a name (used to namespace the on-disk cache)
+/// and the index URL as configured.
+#[derive(Debug, Clone)]
+pub struct RegistryIndex {
+    name: String,
+    url: String,
+}
+
+impl RegistryIndex {
+    fn new(name: String, url: String) -> Self {
+        RegistryIndex { name, url }
+    }
+
+    /// A local override for whether this registry requires authentication,
+    /// read from `registries.<name>.auth-required` in the user's own
+    /// `config.toml`.
+    ///
+    /// This is *not* how real cargo discovers `auth-required` — cargo reads
+    /// it from the registry's own served `config.json` per RFC 3139, which
+    /// callers should prefer. This only exists as an escape hatch for
+    /// registries that can't be reached to ask (e.g. while offline).
+    pub fn local_auth_override(&self) -> Result<Option<bool>> {
+        config_bool(&self.name, "auth-required")
+    }
+
+    /// The token to authenticate index requests with, if one is configured.
+    ///
+    /// Checked in the same order cargo itself uses: the
+    /// `CARGO_REGISTRIES_<NAME>_TOKEN` environment variable, then
+    /// `registries.<name>.token` in `$CARGO_HOME/credentials.toml` (falling
+    /// back to `config.toml` for registries that keep it there).
+    pub fn token(&self) -> Result<Option<String>> {
+        let var = format!("CARGO_REGISTRIES_{}_TOKEN", shouty_snake_case(&self.name));
+        if let Ok(token) = env::var(&var) {
+            return Ok(Some(token));
+        }
+        config_string(&self.name, "token")
+    }
+
+    /// The protocol this index is served over.
+    pub fn protocol(&self) -> IndexProtocol {
+        if self.url.starts_with("sparse+")
+            || env::var(format!(
+                "CARGO_REGISTRIES_{}_PROTOCOL",
+                shouty_snake_case(&self.name)
+            ))
+            .map(|p| p == "sparse")
+            .unwrap_or(false)
+        {
+            IndexProtocol::Sparse
+        } else {
+            IndexProtocol::Git
+        }
+    }
+
+    /// The base URL to fetch summaries from, with any `sparse+` prefix
+    /// stripped and a trailing slash guaranteed, so callers can join paths
+    /// onto it directly without worrying whether the configured index URL
+    /// already ended in `/`.
+    pub fn base_url(&self) -> String {
+        let stripped = self.url.strip_prefix("sparse+").unwrap_or(&self.url);
+        if stripped.ends_with('/') {
+            stripped.to_string()
+        } else {
+            format!("{}/", stripped)
+        }
+    }
+
+    /// Name of the registry, as used for the cache directory and messages.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The directory this registry's index (git checkout, or sparse cache)
+    /// is stored under: `$CARGO_HOME/registry/index/<host>-<hash>`, hashed
+    /// from the index URL, following the `<host>-<hash>` naming cargo uses
+    /// for its own checkouts.
+    ///
+    /// Note this is cargo-edit's own cache, independent of any real checkout
+    /// cargo itself may have made — cargo's exact hashing algorithm isn't
+    /// public API, so there's no guarantee of colliding with cargo's
+    /// directory name, only of being stable across cargo-edit's own runs.
+    pub fn cache_path(&self) -> Result<PathBuf> {
+        let cargo_home = home::cargo_home()?;
+        Ok(cargo_home
+            .join("registry")
+            .join("index")
+            .join(self.cache_dir_name()))
+    }
+
+    /// `<host>-<hash>`, where `hash` is a hex-encoded hash of the full index
+    /// URL (including any `sparse+` prefix, so the git and sparse caches for
+    /// the same host don't collide).
+    fn cache_dir_name(&self) -> String {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.url.hash(&mut hasher);
+
+        format!("{}-{:016x}", url_host(&self.base_url()), hasher.finish())
+    }
+
+    /// Where sparse index responses are cached, one file per crate.
+    pub fn sparse_cache_path(&self) -> Result<PathBuf> {
+        Ok(self.cache_path()?.join(".cache"))
+    }
+}
+
+impl fmt::Display for RegistryIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl AsRef<str> for RegistryIndex {
+    fn as_ref(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Look up `registries.<name>.<key>` as a string, checking
+/// `credentials.toml` first (where tokens usually live) and then
+/// `config.toml`, across every `$CARGO_HOME` config file cargo itself reads.
+fn config_string(name: &str, key: &str) -> Result<Option<String>> {
+    Ok(config_value(name, key)?.and_then(|v| v.as_str().map(str::to_string)))
+}
+
+/// Look up `registries.<name>.<key>` as a bool.
+fn config_bool(name: &str, key: &str) -> Result<Option<bool>> {
+    Ok(config_value(name, key)?.and_then(|v| v.as_bool()))
+}
+
+/// Extract the host from a URL without pulling in a URL-parsing dependency.
+fn url_host(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    without_scheme
+        .split(['/', ':'])
+        .next()
+        .filter(|host| !host.is_empty())
+        .unwrap_or("registry")
+        .to_string()
+}
+
+#[test]
+fn url_host_strips_scheme_path_and_port() {
+    assert_eq!(url_host("https://index.crates.io/"), "index.crates.io");
+    assert_eq!(url_host("https://github.com/rust-lang/foo"), "github.com");
+    assert_eq!(url_host("https://example.com:8080/foo"), "example.com");
+}
+
+#[test]
+fn cache_dir_name_is_stable_and_separates_protocols() {
+    let git = RegistryIndex::new(
+        "crates.io".to_string(),
+        "https://github.com/rust-lang/crates.io-index".to_string(),
+    );
+    let sparse = RegistryIndex::new(
+        "crates.io".to_string(),
+        "sparse+https://index.crates.io/".to_string(),
+    );
+
+    // Deterministic across calls...
+    assert_eq!(git.cache_dir_name(), git.cache_dir_name());
+    // ...but distinct between the git and sparse protocol for the same host.
+    assert_ne!(git.cache_dir_name(), sparse.cache_dir_name());
+    assert!(sparse.cache_dir_name().starts_with("index.crates.io-"));
+}
+
+#[test]
+fn cratesio_defaults_to_sparse_protocol() {
+    let index = RegistryReq::CratesIo.index_url().unwrap();
+    assert_eq!(index.protocol(), IndexProtocol::Sparse);
+}
+
+#[test]
+fn token_is_read_from_env_var() {
+    let index = RegistryIndex::new(
+        "my-registry".to_string(),
+        "sparse+https://example.com/".to_string(),
+    );
+
+    env::set_var("CARGO_REGISTRIES_MY_REGISTRY_TOKEN", "secret-token");
+    let token = index.token().unwrap();
+    env::remove_var("CARGO_REGISTRIES_MY_REGISTRY_TOKEN");
+
+    assert_eq!(token.as_deref(), Some("secret-token"));
+}
+
+fn config_value(name: &str, key: &str) -> Result<Option<toml::Value>> {
+    let cargo_home = home::cargo_home()?;
+    for file in ["credentials.toml", "credentials", "config.toml", "config"] {
+        let path = cargo_home.join(file);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let parsed: toml::Value = content
+            .parse()
+            .chain_err(|| format!("unable to parse `{}`", path.display()))?;
+        let value = parsed
+            .get("registries")
+            .and_then(|r| r.get(name))
+            .and_then(|r| r.get(key))
+            .cloned();
+        if value.is_some() {
+            return Ok(value);
+        }
+    }
+    Ok(None)
+}